@@ -12,11 +12,14 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::HashMap;
 use std::time::Duration;
 
 use crate::voting_contract::IVoting;
 use alloy::{
     primitives::{keccak256, utils::parse_ether, Address, Bytes, B256, U256},
+    providers::Provider,
+    rpc::types::Filter,
     signers::local::PrivateKeySigner,
     sol_types::SolValue,
 };
@@ -35,6 +38,16 @@ use voting_contract::IVoting::IVotingInstance;
 /// Timeout for the transaction to be confirmed.
 pub const TX_TIMEOUT: Duration = Duration::from_secs(45);
 
+/// Number of blocks requested per `eth_getLogs` page while back-filling history.
+pub const LOG_PAGE_SIZE: u64 = 1_000;
+
+/// How often to poll for fresh ballots while a proposal is still open.
+pub const VOTE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Stop polling an open proposal after this many consecutive quiet intervals
+/// (no new ballots), rather than idling until the deadline.
+pub const MAX_QUIET_POLLS: u32 = 3;
+
 mod voting_contract {
     alloy::sol!(
         #![sol(rpc, all_derives)]
@@ -100,8 +113,20 @@ async fn main() -> Result<()> {
     // create new proposal
     let proposal_id = create_new_proposal(&client, &voting_contract).await?;
 
-    // prepare and cast votes
-    let casted_votes = prepare_and_cast_votes(&client, &voting_contract, proposal_id).await?;
+    // remember where to start scanning for this proposal's ballots
+    let from_block = client
+        .provider()
+        .get_block_number()
+        .await
+        .context("failed to read current block number")?;
+
+    // cast votes and keep the off-chain preimages needed to reconstruct them
+    let reveals = prepare_and_cast_votes(&client, &voting_contract, proposal_id).await?;
+
+    // reconstruct the witness from the on-chain VoteCast logs so the publisher
+    // proves exactly the ballots that were recorded on-chain
+    let casted_votes =
+        collect_votes(&client, &voting_contract, proposal_id, from_block, &reveals).await?;
 
     // build request id using the proposal_id as nonce/index
     let request_id =
@@ -155,11 +180,11 @@ async fn main() -> Result<()> {
     let proposal_meta_data = get_proposal_meta_data(&voting_contract, proposal_id).await?;
 
     tracing::info!(
-        "Proposal {:?} tallied: {:?}. with yes votes: {:?}, no votes: {:?}",
+        "Proposal {:?} tallied: {:?}, passed: {:?}. with per-option weights: {:?}",
         proposal_id,
         proposal_meta_data.tallied,
-        proposal_meta_data.yesCount,
-        proposal_meta_data.noCount
+        proposal_meta_data.passed,
+        proposal_meta_data.counts
     );
 
     if !proposal_meta_data.tallied {
@@ -210,7 +235,8 @@ async fn cast_vote(
     voting_contract: &IVotingInstance<alloy::providers::DynProvider>,
     vote: &Vote,
 ) -> Result<()> {
-    let commitment = keccak256((vote.voter, vote.choice, vote.proposalId).abi_encode());
+    let commitment =
+        keccak256((vote.voter, vote.optionId, vote.proposalId, vote.salt).abi_encode());
     let call_cast_vote = voting_contract
         .castVote(vote.proposalId, commitment)
         .from(client.caller());
@@ -242,40 +268,292 @@ async fn get_proposal_meta_data(
     Ok(proposal_meta_data)
 }
 
+/// Off-chain registry of revealed ballot preimages, keyed by the salted
+/// on-chain `keccak256((voter, optionId, proposalId, salt))` commitment.
+///
+/// The `VoteCast` event only carries the commitment, so reconstructing a
+/// provable [`VoteWitness`] from the chain requires matching each logged
+/// commitment back to the full [`Vote`] preimage that produced it.
+struct VoteReveals {
+    eligibility_root: B256,
+    weight_root: B256,
+    num_options: u8,
+    approval_option_id: u8,
+    quorum_votes: u32,
+    approval_threshold_bps: u16,
+    by_commitment: HashMap<B256, Vote>,
+}
+
+impl VoteReveals {
+    /// Creates an empty registry bound to the eligibility and weight roots, the
+    /// number of options every proposal offers, which option counts as approval,
+    /// and the quorum / approval rules the tally is judged under.
+    fn new(
+        eligibility_root: B256,
+        weight_root: B256,
+        num_options: u8,
+        approval_option_id: u8,
+        quorum_votes: u32,
+        approval_threshold_bps: u16,
+    ) -> Self {
+        Self {
+            eligibility_root,
+            weight_root,
+            num_options,
+            approval_option_id,
+            quorum_votes,
+            approval_threshold_bps,
+            by_commitment: HashMap::new(),
+        }
+    }
+
+    /// Records the preimage of a ballot under its salted on-chain commitment.
+    fn record(&mut self, vote: Vote) {
+        let commitment =
+            keccak256((vote.voter, vote.optionId, vote.proposalId, vote.salt).abi_encode());
+        self.by_commitment.insert(commitment, vote);
+    }
+}
+
+/// A keccak Merkle tree over a set of leaves, using the same
+/// `keccak256((left, right))` hashing the guest folds its proofs with.
+struct MerkleTree {
+    layers: Vec<Vec<B256>>,
+}
+
+impl MerkleTree {
+    /// Builds the tree over pre-hashed `leaves`, duplicating the last node of
+    /// any odd layer.
+    fn from_leaves(leaves: Vec<B256>) -> Self {
+        let mut layer = leaves;
+        let mut layers = vec![layer.clone()];
+        while layer.len() > 1 {
+            let mut next = Vec::with_capacity(layer.len().div_ceil(2));
+            for pair in layer.chunks(2) {
+                let right = if pair.len() == 2 { pair[1] } else { pair[0] };
+                next.push(keccak256((pair[0], right).abi_encode()));
+            }
+            layers.push(next.clone());
+            layer = next;
+        }
+        Self { layers }
+    }
+
+    /// Merkle root the guest checks each proof against.
+    fn root(&self) -> B256 {
+        *self
+            .layers
+            .last()
+            .and_then(|l| l.first())
+            .expect("merkle tree is non-empty")
+    }
+
+    /// Returns the `(siblings, pathIndex)` proving the leaf at `index`, where a
+    /// set `pathIndex` bit marks the sibling as the left-hand node.
+    fn proof(&self, mut index: usize) -> (Vec<B256>, U256) {
+        let mut siblings = Vec::new();
+        let mut path = U256::ZERO;
+        for (depth, layer) in self.layers.iter().enumerate() {
+            if layer.len() <= 1 {
+                break;
+            }
+            let sibling = if index % 2 == 0 {
+                layer.get(index + 1).copied().unwrap_or(layer[index])
+            } else {
+                path |= U256::from(1) << depth;
+                layer[index - 1]
+            };
+            siblings.push(sibling);
+            index /= 2;
+        }
+        (siblings, path)
+    }
+}
+
 async fn prepare_and_cast_votes(
     client: &Client,
     voting_contract: &IVotingInstance<alloy::providers::DynProvider>,
     proposal_id: u32,
-) -> Result<VoteWitness> {
-    // manual dummy inputs
-    let votes = VoteWitness {
-        proposalId: proposal_id,
-        votes: vec![
-            Vote {
-                proposalId: proposal_id,
-                voter: Address::from([0x01u8; 20]),
-                choice: true,
-            },
-            Vote {
-                proposalId: proposal_id,
-                voter: Address::from([0x02u8; 20]),
-                choice: false,
-            },
-            Vote {
-                proposalId: proposal_id,
-                voter: Address::from([0x03u8; 20]),
-                choice: true,
-            },
-        ],
-    };
+) -> Result<VoteReveals> {
+    // manual dummy inputs: the eligible voter set, their stake weights, and
+    // how each one voted
+    let voters = [
+        Address::from([0x01u8; 20]),
+        Address::from([0x02u8; 20]),
+        Address::from([0x03u8; 20]),
+    ];
+    let weights = [U256::from(10), U256::from(20), U256::from(30)];
+    // binary ballot encoded as two options: 0 = no, 1 = yes
+    let num_options: u8 = 2;
+    let approval_option_id: u8 = 1;
+    let options = [1u8, 0u8, 1u8];
+    // ballot rules: at least two participants and a simple weighted majority
+    let quorum_votes: u32 = 2;
+    let approval_threshold_bps: u16 = 5000;
+    // secret salts kept off-chain so the commitment preimage is not brute-forceable
+    let salts = [
+        B256::repeat_byte(0xa1),
+        B256::repeat_byte(0xb2),
+        B256::repeat_byte(0xc3),
+    ];
+
+    // publish the allowlist and the weight set the guest checks every ballot
+    // against
+    let eligibility_tree =
+        MerkleTree::from_leaves(voters.iter().map(|v| keccak256(v.abi_encode())).collect());
+    let weight_tree = MerkleTree::from_leaves(
+        voters
+            .iter()
+            .zip(&weights)
+            .map(|(v, w)| keccak256((*v, *w).abi_encode()))
+            .collect(),
+    );
+    let mut reveals = VoteReveals::new(
+        eligibility_tree.root(),
+        weight_tree.root(),
+        num_options,
+        approval_option_id,
+        quorum_votes,
+        approval_threshold_bps,
+    );
 
-    tracing::info!("Casting {} votes", votes.votes.len());
-    for vote in &votes.votes {
-        cast_vote(client, voting_contract, vote).await?;
+    tracing::info!("Casting {} votes", voters.len());
+    for (i, ((&voter, &weight), &option_id)) in
+        voters.iter().zip(&weights).zip(&options).enumerate()
+    {
+        let (siblings, path_index) = eligibility_tree.proof(i);
+        let (weight_siblings, weight_path_index) = weight_tree.proof(i);
+        let vote = Vote {
+            proposalId: proposal_id,
+            voter,
+            optionId: option_id,
+            weight,
+            salt: salts[i],
+            siblings,
+            pathIndex: path_index,
+            weightSiblings: weight_siblings,
+            weightPathIndex: weight_path_index,
+        };
+        cast_vote(client, voting_contract, &vote).await?;
+        reveals.record(vote);
     }
     tracing::info!("All votes committed");
 
-    Ok(votes)
+    Ok(reveals)
+}
+
+/// Reconstructs the [`VoteWitness`] for `proposal_id` from the chain by scanning
+/// `VoteCast` logs from `from_block` onwards.
+///
+/// Historical ranges are paged through with `get_logs`; while the proposal is
+/// still open we keep polling for new logs every [`VOTE_POLL_INTERVAL`] until
+/// its deadline passes or [`MAX_QUIET_POLLS`] consecutive polls bring no new
+/// ballot, mirroring the `eth_getFilterChanges` poll-until-done pattern. After
+/// such an early stop, ballots cast later before the deadline are not
+/// collected. Each
+/// logged commitment is resolved back to its revealed [`Vote`] preimage via
+/// `reveals`, so the witness proves exactly the ballots recorded on-chain.
+async fn collect_votes(
+    client: &Client,
+    voting_contract: &IVotingInstance<alloy::providers::DynProvider>,
+    proposal_id: u32,
+    from_block: u64,
+    reveals: &VoteReveals,
+) -> Result<VoteWitness> {
+    let provider = client.provider();
+    let deadline = voting_contract
+        .proposalDeadline(proposal_id)
+        .call()
+        .await
+        .context("failed to read proposal deadline")?;
+
+    let mut votes = Vec::new();
+    let mut next_block = from_block;
+    let mut quiet_polls = 0u32;
+
+    loop {
+        let collected_before = votes.len();
+        let latest = provider
+            .get_block_number()
+            .await
+            .context("failed to read latest block number")?;
+
+        while next_block <= latest {
+            let to_block = (next_block + LOG_PAGE_SIZE - 1).min(latest);
+            let filter = Filter::new()
+                .address(*voting_contract.address())
+                .event_signature(IVoting::VoteCast::SIGNATURE_HASH)
+                .topic1(B256::from(U256::from(proposal_id)))
+                .from_block(next_block)
+                .to_block(to_block);
+
+            let logs = provider
+                .get_logs(&filter)
+                .await
+                .context("failed to fetch VoteCast logs")?;
+
+            for log in logs {
+                let event = IVoting::VoteCast::decode_log(&log.inner)
+                    .context("failed to decode VoteCast log")?;
+                let vote = reveals
+                    .by_commitment
+                    .get(&event.commitment)
+                    .cloned()
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "no revealed preimage for on-chain commitment {:?}",
+                            event.commitment
+                        )
+                    })?;
+                votes.push(vote);
+            }
+
+            next_block = to_block + 1;
+        }
+
+        // Stop once the proposal is closed.
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        if U256::from(now) >= deadline {
+            break;
+        }
+
+        // Otherwise poll for late ballots, but don't idle out the whole voting
+        // window: give up once several consecutive intervals bring nothing new.
+        if votes.len() == collected_before {
+            quiet_polls += 1;
+            if quiet_polls >= MAX_QUIET_POLLS {
+                tracing::info!(
+                    "No new votes for {} polls, stopping collection early",
+                    quiet_polls
+                );
+                break;
+            }
+        } else {
+            quiet_polls = 0;
+        }
+
+        tracing::info!(
+            "Proposal {} still open, polling for new votes in {}s",
+            proposal_id,
+            VOTE_POLL_INTERVAL.as_secs()
+        );
+        tokio::time::sleep(VOTE_POLL_INTERVAL).await;
+    }
+
+    tracing::info!("Collected {} votes from chain", votes.len());
+    Ok(VoteWitness {
+        eligibilityRoot: reveals.eligibility_root,
+        weightRoot: reveals.weight_root,
+        numOptions: reveals.num_options,
+        approvalOptionId: reveals.approval_option_id,
+        quorumVotes: reveals.quorum_votes,
+        approvalThresholdBps: reveals.approval_threshold_bps,
+        proposalIds: vec![proposal_id],
+        votes,
+    })
 }
 
 async fn request_boundless_proof(
@@ -1,55 +1,198 @@
-use alloy_primitives::{keccak256, Address, U256};
+use alloy_primitives::{keccak256, Address, B256, U256};
 use alloy_sol_types::SolValue;
 use guests::VOTING_TALLY_ELF;
 use risc0_zkvm::{default_executor, ExecutorEnv};
 use vote_types::{Vote, VotePublicOutput, VoteWitness};
 
-#[test]
-fn tally_votes_basic() {
-    let proposal_id = U256::from(0);
-    // manual dummy inputs
-    let input = VoteWitness {
-        proposalId: proposal_id,
-        votes: vec![
-            Vote {
-                proposalId: proposal_id,
-                voter: Address::from([0x01u8; 20]),
-                choice: true,
-            },
-            Vote {
-                proposalId: proposal_id,
-                voter: Address::from([0x02u8; 20]),
-                choice: false,
-            },
-            Vote {
-                proposalId: proposal_id,
-                voter: Address::from([0x03u8; 20]),
-                choice: true,
-            },
-        ],
-    };
+/// Builds a keccak Merkle tree over `leaves`, matching the guest's folding, and
+/// returns the root together with a `(siblings, pathIndex)` proof per leaf.
+fn merkle_tree(leaves: Vec<B256>) -> (B256, Vec<(Vec<B256>, U256)>) {
+    let count = leaves.len();
+    let mut layers: Vec<Vec<B256>> = vec![leaves];
+    while layers.last().unwrap().len() > 1 {
+        let layer = layers.last().unwrap();
+        let next = layer
+            .chunks(2)
+            .map(|pair| {
+                let right = if pair.len() == 2 { pair[1] } else { pair[0] };
+                keccak256((pair[0], right).abi_encode())
+            })
+            .collect();
+        layers.push(next);
+    }
+    let root = layers.last().unwrap()[0];
+
+    let proofs = (0..count)
+        .map(|mut index| {
+            let mut siblings = Vec::new();
+            let mut path = U256::ZERO;
+            for (depth, layer) in layers.iter().enumerate() {
+                if layer.len() <= 1 {
+                    break;
+                }
+                let sibling = if index % 2 == 0 {
+                    layer.get(index + 1).copied().unwrap_or(layer[index])
+                } else {
+                    path |= U256::from(1) << depth;
+                    layer[index - 1]
+                };
+                siblings.push(sibling);
+                index /= 2;
+            }
+            (siblings, path)
+        })
+        .collect();
+
+    (root, proofs)
+}
+
+/// Proposal id shared by the fixtures below.
+const PROPOSAL_ID: u32 = 0;
+
+/// Builds a valid three-voter binary ballot (0 = no, 1 = yes) with correct
+/// eligibility and weight Merkle proofs. Negative tests clone this and tamper
+/// with a single field.
+fn sample_witness() -> VoteWitness {
+    let voters = [
+        Address::from([0x01u8; 20]),
+        Address::from([0x02u8; 20]),
+        Address::from([0x03u8; 20]),
+    ];
+    let weights = [U256::from(10), U256::from(20), U256::from(30)];
+    let options = [1u8, 0u8, 1u8];
+    let salts = [
+        B256::repeat_byte(0xa1),
+        B256::repeat_byte(0xb2),
+        B256::repeat_byte(0xc3),
+    ];
+
+    let (eligibility_root, elig_proofs) =
+        merkle_tree(voters.iter().map(|v| keccak256(v.abi_encode())).collect());
+    let (weight_root, weight_proofs) = merkle_tree(
+        voters
+            .iter()
+            .zip(&weights)
+            .map(|(v, w)| keccak256((*v, *w).abi_encode()))
+            .collect(),
+    );
+
+    let votes: Vec<Vote> = (0..voters.len())
+        .map(|i| Vote {
+            proposalId: PROPOSAL_ID,
+            voter: voters[i],
+            optionId: options[i],
+            weight: weights[i],
+            salt: salts[i],
+            siblings: elig_proofs[i].0.clone(),
+            pathIndex: elig_proofs[i].1,
+            weightSiblings: weight_proofs[i].0.clone(),
+            weightPathIndex: weight_proofs[i].1,
+        })
+        .collect();
 
+    VoteWitness {
+        eligibilityRoot: eligibility_root,
+        weightRoot: weight_root,
+        numOptions: 2,
+        approvalOptionId: 1,
+        quorumVotes: 2,
+        approvalThresholdBps: 5000,
+        proposalIds: vec![PROPOSAL_ID],
+        votes,
+    }
+}
+
+/// Runs the guest and decodes its journal; panics if the guest rejects.
+fn tally(input: &VoteWitness) -> VotePublicOutput {
     let env = ExecutorEnv::builder()
         .write_slice(&input.abi_encode())
         .build()
         .unwrap();
-
     let session_info = default_executor().execute(env, VOTING_TALLY_ELF).unwrap();
+    VotePublicOutput::abi_decode(&session_info.journal.bytes).unwrap()
+}
 
-    let out = VotePublicOutput::abi_decode(&session_info.journal.bytes).unwrap();
-    assert_eq!(out.proposalId, proposal_id);
-    assert_eq!(out.yes, 2);
-    assert_eq!(out.no, 1);
+/// Runs the guest and reports whether it rejected the witness (a guest
+/// assertion surfaces as an execution error on the host).
+fn guest_rejects(input: &VoteWitness) -> bool {
+    let env = ExecutorEnv::builder()
+        .write_slice(&input.abi_encode())
+        .build()
+        .unwrap();
+    default_executor().execute(env, VOTING_TALLY_ELF).is_err()
+}
 
-    let mut expected_digest = keccak256((proposal_id).abi_encode());
-    println!(
-        "Expected digest calculation starts with: {:?}",
-        expected_digest
-    );
+#[test]
+fn tally_votes_basic() {
+    let input = sample_witness();
+    let out = tally(&input);
+
+    assert_eq!(out.numOptions, input.numOptions);
+    assert_eq!(out.eligibilityRoot, input.eligibilityRoot);
+    assert_eq!(out.weightRoot, input.weightRoot);
+    assert_eq!(out.proposals.len(), 1);
 
+    let proposal = &out.proposals[0];
+    assert_eq!(proposal.proposalId, PROPOSAL_ID);
+    // option 0 (no): voter 1 weight 20; option 1 (yes): voters 0 and 2 weights 10 + 30
+    assert_eq!(proposal.counts, vec![U256::from(20), U256::from(40)]);
+    // 3 ballots clear the quorum of 2 and 40/60 of the weight approves (> 50%)
+    assert!(proposal.passed);
+
+    let mut expected_digest = keccak256((PROPOSAL_ID).abi_encode());
     for vote in &input.votes {
-        let vote_commitment = keccak256((vote.voter, vote.choice, vote.proposalId).abi_encode());
+        let vote_commitment =
+            keccak256((vote.voter, vote.optionId, vote.proposalId, vote.salt).abi_encode());
         expected_digest = keccak256((expected_digest, vote_commitment).abi_encode());
     }
-    assert_eq!(out.commitmentsDigest, expected_digest);
+    assert_eq!(proposal.commitmentsDigest, expected_digest);
+}
+
+#[test]
+fn rejects_ineligible_voter() {
+    // Tamper with a voter's eligibility proof so it no longer folds to the root.
+    let mut input = sample_witness();
+    input.votes[0].siblings[0] = B256::repeat_byte(0xff);
+    assert!(guest_rejects(&input));
+}
+
+#[test]
+fn rejects_tampered_weight_proof() {
+    // A voter claiming a weight not proven against the weight root is rejected.
+    let mut input = sample_witness();
+    input.votes[0].weight = U256::from(1_000_000);
+    assert!(guest_rejects(&input));
+}
+
+#[test]
+fn rejects_double_vote() {
+    // A voter appearing twice for the same proposal collides on its nullifier.
+    let mut input = sample_witness();
+    let duplicate = input.votes[0].clone();
+    input.votes.push(duplicate);
+    assert!(guest_rejects(&input));
+}
+
+#[test]
+fn fails_when_quorum_not_met() {
+    // Require more ballots than were cast: participation falls short.
+    let mut input = sample_witness();
+    input.quorumVotes = 10;
+    assert!(!tally(&input).proposals[0].passed);
+}
+
+#[test]
+fn fails_when_threshold_not_met() {
+    // 40/60 approves (6666 bps); an 80% threshold is not reached.
+    let mut input = sample_witness();
+    input.approvalThresholdBps = 8000;
+    assert!(!tally(&input).proposals[0].passed);
+}
+
+#[test]
+fn rejects_option_out_of_range() {
+    // An optionId at or beyond numOptions must be rejected.
+    let mut input = sample_witness();
+    input.votes[0].optionId = input.numOptions;
+    assert!(guest_rejects(&input));
 }
@@ -5,20 +5,43 @@ sol! {
     struct Vote {
         uint32 proposalId;
         address voter;
-        bool choice;
+        uint8 optionId;
+        uint256 weight;
+        bytes32 salt;
+        bytes32[] siblings;
+        uint256 pathIndex;
+        bytes32[] weightSiblings;
+        uint256 weightPathIndex;
     }
 
     #[derive(Debug)]
     struct VoteWitness {
-        uint32 proposalId;
+        bytes32 eligibilityRoot;
+        bytes32 weightRoot;
+        uint8 numOptions;
+        uint8 approvalOptionId;
+        uint32 quorumVotes;
+        uint16 approvalThresholdBps;
+        uint32[] proposalIds;
         Vote[] votes;
     }
 
     #[derive(Debug)]
-    struct VotePublicOutput {
+    struct ProposalOutput {
         uint32 proposalId;
         bytes32 commitmentsDigest;
-        uint32 yes;
-        uint32 no;
+        uint256[] counts;
+        bool passed;
+    }
+
+    #[derive(Debug)]
+    struct VotePublicOutput {
+        bytes32 eligibilityRoot;
+        bytes32 weightRoot;
+        uint8 numOptions;
+        uint8 approvalOptionId;
+        uint32 quorumVotes;
+        uint16 approvalThresholdBps;
+        ProposalOutput[] proposals;
     }
 }
@@ -1,40 +1,139 @@
 #![no_main]
 
-use alloy_primitives::keccak256;
+use alloy_primitives::{keccak256, B256, U256};
 use alloy_sol_types::SolValue;
 use risc0_zkvm::guest::env;
 use std::io::Read;
-use vote_types::{VotePublicOutput, VoteWitness};
+use vote_types::{ProposalOutput, VotePublicOutput, VoteWitness};
 
 risc0_zkvm::guest::entry!(main);
 
+/// Folds a Merkle proof up from the leaf `node`, selecting the sibling side from each bit
+/// of `path` (a set bit places the sibling on the left).
+fn fold_proof(mut node: B256, siblings: &[B256], path: U256) -> B256 {
+    for (i, sibling) in siblings.iter().enumerate() {
+        node = if path.bit(i) {
+            keccak256((*sibling, node).abi_encode())
+        } else {
+            keccak256((node, *sibling).abi_encode())
+        };
+    }
+    node
+}
+
 fn main() {
     let mut input_bytes = Vec::<u8>::new();
     env::stdin().read_to_end(&mut input_bytes).unwrap();
 
-    let VoteWitness { proposalId, votes } = VoteWitness::abi_decode(&input_bytes).unwrap();
+    let VoteWitness {
+        eligibilityRoot,
+        weightRoot,
+        numOptions,
+        approvalOptionId,
+        quorumVotes,
+        approvalThresholdBps,
+        proposalIds,
+        votes,
+    } = VoteWitness::abi_decode(&input_bytes).unwrap();
 
-    let mut yes: u32 = 0;
-    let mut no: u32 = 0;
-    let mut digest = keccak256((proposalId).abi_encode());
+    // Per-proposal accumulators: a weighted sum per option and a running digest
+    // of the salted commitments, so one proof settles the whole ballot batch.
+    let num_options = numOptions as usize;
+    assert!(
+        (approvalOptionId as usize) < num_options,
+        "approvalOptionId out of range"
+    );
+    let mut counts: Vec<Vec<U256>> = proposalIds
+        .iter()
+        .map(|_| vec![U256::ZERO; num_options])
+        .collect();
+    let mut digests: Vec<B256> = proposalIds
+        .iter()
+        .map(|pid| keccak256((*pid).abi_encode()))
+        .collect();
+    // Ballot counts drive the participation (quorum) check per proposal.
+    let mut ballots: Vec<u32> = vec![0; proposalIds.len()];
+    // Nullifiers bind one ballot per voter per proposal without revealing choice.
+    let mut nullifiers: Vec<B256> = Vec::new();
     for v in votes {
-        assert_eq!(v.proposalId, proposalId);
-        // TODO: should probably check that voter hasn't already voted and is eligible to vote but lets not worry about that for
-        if v.choice {
-            yes += 1;
-        } else {
-            no += 1;
-        }
+        let pidx = proposalIds
+            .iter()
+            .position(|p| *p == v.proposalId)
+            .expect("vote references a proposal outside the batch");
+        assert!((v.optionId as usize) < num_options, "optionId out of range");
+
+        // Prove the voter belongs to the published eligibility allowlist.
+        let eligibility_leaf = keccak256(v.voter.abi_encode());
+        assert_eq!(
+            fold_proof(eligibility_leaf, &v.siblings, v.pathIndex),
+            eligibilityRoot,
+            "voter not in eligibility allowlist"
+        );
 
-        let vote_commitment = keccak256((v.voter, v.choice, v.proposalId).abi_encode());
-        digest = keccak256((digest, vote_commitment).abi_encode());
+        // Prove the voter's weight against the published weight set.
+        let weight_leaf = keccak256((v.voter, v.weight).abi_encode());
+        assert_eq!(
+            fold_proof(weight_leaf, &v.weightSiblings, v.weightPathIndex),
+            weightRoot,
+            "voter weight not proven"
+        );
+
+        // Derive the voter's nullifier and reject any repeat within the batch;
+        // a voter may still appear once per proposal.
+        let nullifier = keccak256((v.voter, v.proposalId).abi_encode());
+        assert!(
+            !nullifiers.contains(&nullifier),
+            "voter already counted (duplicate nullifier)"
+        );
+        nullifiers.push(nullifier);
+
+        let slot = &mut counts[pidx][v.optionId as usize];
+        *slot = slot.checked_add(v.weight).expect("option weight overflow");
+        ballots[pidx] += 1;
+
+        // Recompute the salted on-chain commitment; the secret salt keeps the
+        // choice private while the digest still binds to the recorded ballots.
+        let vote_commitment =
+            keccak256((v.voter, v.optionId, v.proposalId, v.salt).abi_encode());
+        digests[pidx] = keccak256((digests[pidx], vote_commitment).abi_encode());
     }
 
+    let proposals = proposalIds
+        .iter()
+        .enumerate()
+        .map(|(i, pid)| {
+            // Participation must clear the quorum and the explicitly chosen
+            // approval option must reach the threshold share of the weight.
+            let total_weight = counts[i]
+                .iter()
+                .try_fold(U256::ZERO, |acc, c| acc.checked_add(*c))
+                .expect("total weight overflow");
+            let approve_weight = counts[i][approvalOptionId as usize];
+            let passed = ballots[i] >= quorumVotes
+                && approve_weight
+                    .checked_mul(U256::from(10000u32))
+                    .expect("approval weight overflow")
+                    >= total_weight
+                        .checked_mul(U256::from(approvalThresholdBps))
+                        .expect("threshold weight overflow");
+
+            ProposalOutput {
+                proposalId: *pid,
+                commitmentsDigest: digests[i],
+                counts: counts[i].clone(),
+                passed,
+            }
+        })
+        .collect();
+
     let public_output = VotePublicOutput {
-        proposalId,
-        commitmentsDigest: digest,
-        yes,
-        no,
+        eligibilityRoot,
+        weightRoot,
+        numOptions,
+        approvalOptionId,
+        quorumVotes,
+        approvalThresholdBps,
+        proposals,
     };
     env::commit_slice(public_output.abi_encode().as_slice());
 }